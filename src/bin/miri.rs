@@ -0,0 +1,24 @@
+use miri::eval::{self, MiriConfig};
+
+/// Parses the `-Zmiri-*` flags that configure the `MiriConfig` passed to `miri::eval::eval_main`.
+/// Any other argument is left in `rustc_args` for rustc itself to deal with.
+fn parse_arg_flag(arg: &str, miri_config: &mut MiriConfig, rustc_args: &mut Vec<String>) {
+    if arg == "-Zmiri-disable-isolation" {
+        miri_config.communicate = true;
+    } else if let Some(param) = arg.strip_prefix("-Zmiri-deterministic-clock=") {
+        miri_config.deterministic_clock = Some(eval::parse_deterministic_clock_flag(param));
+    } else {
+        rustc_args.push(arg.to_owned());
+    }
+}
+
+fn main() {
+    let mut miri_config = MiriConfig::default();
+    let mut rustc_args = Vec::new();
+
+    for arg in std::env::args().skip(1) {
+        parse_arg_flag(&arg, &mut miri_config, &mut rustc_args);
+    }
+
+    miri::run(rustc_args, miri_config);
+}
@@ -0,0 +1,42 @@
+use std::time::Duration;
+
+use crate::shims::time::DeterministicClock;
+
+/// Configuration needed to spawn a Miri evaluation context.
+#[derive(Clone)]
+pub struct MiriConfig {
+    /// Determines if validity checking is enabled.
+    pub validate: bool,
+    /// Determines if Stacked Borrows is enabled.
+    pub stacked_borrows: bool,
+    /// Whether to avoid the host's isolation guarantees, e.g. consulting it directly for things
+    /// like time and environment variables.
+    pub communicate: bool,
+    /// If set, clock-reading shims (`clock_gettime`, `gettimeofday`, `GetSystemTimeAsFileTime`,
+    /// `mach_absolute_time`) answer from this deterministic clock instead of requiring
+    /// `communicate` and reading the host. Set via `-Zmiri-deterministic-clock`.
+    pub deterministic_clock: Option<DeterministicClock>,
+}
+
+impl Default for MiriConfig {
+    fn default() -> MiriConfig {
+        MiriConfig { validate: true, stacked_borrows: true, communicate: false, deterministic_clock: None }
+    }
+}
+
+/// Parses the argument to `-Zmiri-deterministic-clock=<epoch-secs>[,<quantum-nanos>]` into a
+/// `DeterministicClock`. `quantum-nanos` defaults to `1` (one nanosecond per monotonic read).
+pub fn parse_deterministic_clock_flag(arg: &str) -> DeterministicClock {
+    let mut parts = arg.splitn(2, ',');
+    let epoch_secs: u64 = parts
+        .next()
+        .unwrap()
+        .parse()
+        .unwrap_or_else(|_| panic!("-Zmiri-deterministic-clock epoch must be a number of seconds"));
+    let quantum_nanos: u64 = match parts.next() {
+        Some(quantum) =>
+            quantum.parse().unwrap_or_else(|_| panic!("-Zmiri-deterministic-clock quantum must be a number of nanoseconds")),
+        None => 1,
+    };
+    DeterministicClock::new(Duration::from_secs(epoch_secs), Duration::from_nanos(quantum_nanos))
+}
@@ -0,0 +1,31 @@
+use std::time::Instant;
+
+use crate::shims::time::DeterministicClock;
+use crate::MiriConfig;
+
+/// The machine-specific state of the interpreter, attached to every `InterpCx` as `machine`.
+///
+/// This only shows the time-related fields consulted by `shims::time`; the rest of the
+/// interpreter state (stacked borrows, file descriptors, environment, ...) lives alongside them.
+pub struct Evaluator<'mir, 'tcx> {
+    /// The `Instant` all monotonic clock shims (`clock_gettime(CLOCK_MONOTONIC)`,
+    /// `mach_absolute_time`, `QueryPerformanceCounter`, ...) measure elapsed time against when no
+    /// `deterministic_clock` is set. The sleep shims in `shims::time` wind this backwards to
+    /// simulate the passage of time; readings of the deterministic clock do not use it.
+    pub(crate) time_anchor: Instant,
+    /// If set, clock-reading shims answer from this deterministic, host-independent clock instead
+    /// of requiring `communicate` and consulting the host. Set via `-Zmiri-deterministic-clock`.
+    pub(crate) deterministic_clock: Option<DeterministicClock>,
+
+    _tcx: std::marker::PhantomData<&'mir &'tcx ()>,
+}
+
+impl<'mir, 'tcx> Evaluator<'mir, 'tcx> {
+    pub(crate) fn new(config: &MiriConfig) -> Self {
+        Evaluator {
+            time_anchor: Instant::now(),
+            deterministic_clock: config.deterministic_clock.clone(),
+            _tcx: std::marker::PhantomData,
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use std::time::{Duration, SystemTime, Instant};
 use std::convert::TryFrom;
+use std::cell::Cell;
 
 use rustc_target::abi::LayoutOf;
 
@@ -7,12 +8,107 @@ use crate::stacked_borrows::Tag;
 use crate::*;
 use helpers::{immty_from_int_checked, immty_from_uint_checked};
 
+/// State for Miri's deterministic clock mode (`-Zmiri-deterministic-clock`).
+/// Rather than consulting the host clock, `CLOCK_REALTIME`-like queries start from a fixed,
+/// configurable epoch, and every monotonic read advances by a fixed quantum. This keeps programs
+/// that timestamp events reproducible and host-independent, even when isolation would otherwise
+/// forbid touching the clock at all.
+#[derive(Clone)]
+pub struct DeterministicClock {
+    /// The `Duration` since the Unix epoch that `CLOCK_REALTIME`-like queries are anchored to.
+    epoch: Duration,
+    /// How far the virtual monotonic clock advances on every read.
+    quantum: Duration,
+    /// Number of quanta handed out so far.
+    ticks: Cell<u64>,
+}
+
+impl DeterministicClock {
+    pub fn new(epoch: Duration, quantum: Duration) -> Self {
+        DeterministicClock { epoch, quantum, ticks: Cell::new(0) }
+    }
+
+    /// Returns the current monotonic reading and advances the clock by one quantum.
+    fn monotonic(&self) -> Duration {
+        let ticks = self.ticks.get();
+        self.ticks.set(ticks + 1);
+        self.quantum.saturating_mul(u32::try_from(ticks).unwrap_or(u32::MAX))
+    }
+
+    /// Returns the current "realtime" reading, derived from the same monotonic ticks.
+    fn realtime(&self) -> Duration {
+        self.epoch + self.monotonic()
+    }
+}
+
 /// Returns the time elapsed between the provided time and the unix epoch as a `Duration`.
 pub fn system_time_to_duration<'tcx>(time: &SystemTime) -> InterpResult<'tcx, Duration> {
     time.duration_since(SystemTime::UNIX_EPOCH)
         .map_err(|_| err_unsup_format!("times before the Unix epoch are not supported").into())
 }
 
+/// Reads a `timespec` struct and converts it into a `Duration`, returning `None` if `tv_sec` is
+/// negative or `tv_nsec` is outside `[0, 999999999]` -- callers should turn that into `EINVAL`
+/// rather than aborting the interpretation, matching what the real syscalls do.
+fn read_timespec<'mir, 'tcx: 'mir>(
+    this: &mut MiriEvalContext<'mir, 'tcx>,
+    tp: OpTy<'tcx, Tag>,
+) -> InterpResult<'tcx, Option<Duration>> {
+    let tp = this.deref_operand(tp)?;
+    let tv_sec = this.read_scalar(this.mplace_field(tp, 0)?.into())?.to_machine_isize(this)?;
+    let tv_nsec = this.read_scalar(this.mplace_field(tp, 1)?.into())?.to_machine_isize(this)?;
+
+    let tv_sec = match u64::try_from(tv_sec) {
+        Ok(tv_sec) => tv_sec,
+        Err(_) => return Ok(None),
+    };
+    let tv_nsec = match u32::try_from(tv_nsec) {
+        Ok(tv_nsec) if tv_nsec < 1_000_000_000 => tv_nsec,
+        _ => return Ok(None),
+    };
+
+    Ok(Some(Duration::new(tv_sec, tv_nsec)))
+}
+
+/// Returns the current reading of either the realtime or monotonic clock. Consults the
+/// deterministic clock if one is configured; otherwise falls back to the host, which requires
+/// isolation to be disabled.
+fn current_clock_reading<'mir, 'tcx: 'mir>(
+    this: &mut MiriEvalContext<'mir, 'tcx>,
+    realtime: bool,
+    who: &str,
+) -> InterpResult<'tcx, Duration> {
+    if let Some(clock) = &this.machine.deterministic_clock {
+        Ok(if realtime { clock.realtime() } else { clock.monotonic() })
+    } else {
+        this.check_no_isolation(who)?;
+        if realtime {
+            system_time_to_duration(&SystemTime::now())
+        } else {
+            // Absolute time does not matter, only relative time does, so we can just
+            // use our own time anchor here.
+            Ok(Instant::now().duration_since(this.machine.time_anchor))
+        }
+    }
+}
+
+/// Advances the virtual monotonic clock backing all the sleep shims by `duration`, without
+/// actually blocking the host. Returns an unsupported-format error instead of panicking if
+/// `duration` is so large it would underflow the time anchor. Only the time anchor is moved: the
+/// deterministic clock (if configured) does not read it, so its readings are not affected by
+/// sleeps.
+fn advance_virtual_clock<'mir, 'tcx: 'mir>(
+    this: &mut MiriEvalContext<'mir, 'tcx>,
+    duration: Duration,
+) -> InterpResult<'tcx> {
+    this.machine.time_anchor = this
+        .machine
+        .time_anchor
+        .checked_sub(duration)
+        .ok_or_else(|| err_unsup_format!("sleeping for this long is not supported"))?;
+    Ok(())
+}
+
 impl<'mir, 'tcx> EvalContextExt<'mir, 'tcx> for crate::MiriEvalContext<'mir, 'tcx> {}
 pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx> {
     fn clock_gettime(
@@ -22,23 +118,32 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
     ) -> InterpResult<'tcx, i32> {
         let this = self.eval_context_mut();
 
-        this.assert_target_os("linux", "clock_gettime");
-        this.check_no_isolation("clock_gettime")?;
+        let target_os = this.tcx.sess.target.os.as_str();
+        if target_os != "linux" && target_os != "macos" {
+            return Err(err_unsup_format!("`clock_gettime` is only available for the `linux` and `macos` targets").into());
+        }
+        let is_linux = target_os == "linux";
 
         let clk_id = this.read_scalar(clk_id_op)?.to_i32()?;
         let tp = this.deref_operand(tp_op)?;
 
-        let duration = if clk_id == this.eval_libc_i32("CLOCK_REALTIME")? {
-            system_time_to_duration(&SystemTime::now())?
-        } else if clk_id == this.eval_libc_i32("CLOCK_MONOTONIC")? {
-            // Absolute time does not matter, only relative time does, so we can just
-            // use our own time anchor here.
-            Instant::now().duration_since(this.machine.time_anchor)
-        } else {
+        // `CLOCK_{REALTIME,MONOTONIC}_COARSE` only exist on Linux; we alias them to the same
+        // sources as their non-coarse counterparts since Miri does not model reduced resolution.
+        let realtime = clk_id == this.eval_libc_i32("CLOCK_REALTIME")?
+            || (is_linux && clk_id == this.eval_libc_i32("CLOCK_REALTIME_COARSE")?);
+        // Miri has no real CPU-time accounting, so CPU-time clocks are backed by the same
+        // monotonic anchor as `CLOCK_MONOTONIC`.
+        let monotonic = clk_id == this.eval_libc_i32("CLOCK_MONOTONIC")?
+            || clk_id == this.eval_libc_i32("CLOCK_PROCESS_CPUTIME_ID")?
+            || clk_id == this.eval_libc_i32("CLOCK_THREAD_CPUTIME_ID")?
+            || (is_linux && clk_id == this.eval_libc_i32("CLOCK_MONOTONIC_COARSE")?);
+        if !realtime && !monotonic {
             let einval = this.eval_libc("EINVAL")?;
             this.set_last_error(einval)?;
             return Ok(-1);
-        };
+        }
+
+        let duration = current_clock_reading(this, realtime, "clock_gettime")?;
 
         let tv_sec = duration.as_secs();
         let tv_nsec = duration.subsec_nanos();
@@ -61,7 +166,6 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
         let this = self.eval_context_mut();
 
         this.assert_target_os("macos", "gettimeofday");
-        this.check_no_isolation("gettimeofday")?;
 
         // Using tz is obsolete and should always be null
         let tz = this.read_scalar(tz_op)?.not_undef()?;
@@ -73,7 +177,12 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
 
         let tv = this.deref_operand(tv_op)?;
 
-        let duration = system_time_to_duration(&SystemTime::now())?;
+        let duration = if let Some(clock) = &this.machine.deterministic_clock {
+            clock.realtime()
+        } else {
+            this.check_no_isolation("gettimeofday")?;
+            system_time_to_duration(&SystemTime::now())?
+        };
         let tv_sec = duration.as_secs();
         let tv_usec = duration.subsec_micros();
 
@@ -92,7 +201,6 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
         let this = self.eval_context_mut();
 
         this.assert_target_os("windows", "GetSystemTimeAsFileTime");
-        this.check_no_isolation("GetSystemTimeAsFileTime")?;
 
         let NANOS_PER_SEC = this.eval_windows_u64("time", "NANOS_PER_SEC")?;
         let INTERVALS_PER_SEC = this.eval_windows_u64("time", "INTERVALS_PER_SEC")?;
@@ -100,7 +208,13 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
         let NANOS_PER_INTERVAL = NANOS_PER_SEC / INTERVALS_PER_SEC;
         let SECONDS_TO_UNIX_EPOCH = INTERVALS_TO_UNIX_EPOCH / INTERVALS_PER_SEC;
 
-        let duration = system_time_to_duration(&SystemTime::now())? + Duration::from_secs(SECONDS_TO_UNIX_EPOCH);
+        let unix_duration = if let Some(clock) = &this.machine.deterministic_clock {
+            clock.realtime()
+        } else {
+            this.check_no_isolation("GetSystemTimeAsFileTime")?;
+            system_time_to_duration(&SystemTime::now())?
+        };
+        let duration = unix_duration + Duration::from_secs(SECONDS_TO_UNIX_EPOCH);
         let duration_ticks = u64::try_from(duration.as_nanos() / u128::from(NANOS_PER_INTERVAL))
             .map_err(|_| err_unsup_format!("programs running more than 2^64 Windows ticks after the Windows epoch are not supported"))?;
 
@@ -115,16 +229,180 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
         Ok(())
     }
 
+    #[allow(non_snake_case)]
+    fn QueryPerformanceCounter(
+        &mut self,
+        lpPerformanceCount_op: OpTy<'tcx, Tag>,
+    ) -> InterpResult<'tcx, i32> {
+        let this = self.eval_context_mut();
+
+        this.assert_target_os("windows", "QueryPerformanceCounter");
+
+        // This is what `std::time::Instant` uses on Windows, so we mirror the macos
+        // `mach_absolute_time` monotonic path: the counter is derived from our own time anchor
+        // rather than the host clock, in 100ns intervals (see `QueryPerformanceFrequency`).
+        let duration = if let Some(clock) = &this.machine.deterministic_clock {
+            clock.monotonic()
+        } else {
+            this.check_no_isolation("QueryPerformanceCounter")?;
+            Instant::now().duration_since(this.machine.time_anchor)
+        };
+        let count = i64::try_from(duration.as_nanos() / 100)
+            .map_err(|_| err_unsup_format!("programs running longer than 2^63 100ns intervals are not supported"))?;
+
+        let imm = immty_from_int_checked(count, this.libc_ty_layout("c_longlong")?)?;
+        this.write_packed_immediates(this.deref_operand(lpPerformanceCount_op)?, &[imm])?;
+
+        Ok(1) // TRUE
+    }
+
+    #[allow(non_snake_case)]
+    fn QueryPerformanceFrequency(
+        &mut self,
+        lpFrequency_op: OpTy<'tcx, Tag>,
+    ) -> InterpResult<'tcx, i32> {
+        let this = self.eval_context_mut();
+
+        this.assert_target_os("windows", "QueryPerformanceFrequency");
+
+        // The counter above ticks in 100ns intervals, i.e. 10_000_000 times a second.
+        let imm = immty_from_int_checked(10_000_000i64, this.libc_ty_layout("c_longlong")?)?;
+        this.write_packed_immediates(this.deref_operand(lpFrequency_op)?, &[imm])?;
+
+        Ok(1) // TRUE
+    }
+
+    fn nanosleep(
+        &mut self,
+        req_op: OpTy<'tcx, Tag>,
+        _rem_op: OpTy<'tcx, Tag>,
+    ) -> InterpResult<'tcx, i32> {
+        let this = self.eval_context_mut();
+
+        this.assert_target_os("linux", "nanosleep");
+
+        let duration = match read_timespec(this, req_op)? {
+            Some(duration) => duration,
+            None => {
+                let einval = this.eval_libc("EINVAL")?;
+                this.set_last_error(einval)?;
+                return Ok(-1);
+            }
+        };
+        // Rather than actually blocking the host, advance our virtual monotonic clock by the
+        // requested amount so that a caller that polls `CLOCK_MONOTONIC` between sleeps still
+        // sees time pass deterministically.
+        advance_virtual_clock(this, duration)?;
+
+        // We do not support signals, so `nanosleep` always runs to completion and `rem` is left
+        // untouched.
+        Ok(0)
+    }
+
+    fn clock_nanosleep(
+        &mut self,
+        clk_id_op: OpTy<'tcx, Tag>,
+        flags_op: OpTy<'tcx, Tag>,
+        req_op: OpTy<'tcx, Tag>,
+        _rem_op: OpTy<'tcx, Tag>,
+    ) -> InterpResult<'tcx, i32> {
+        let this = self.eval_context_mut();
+
+        this.assert_target_os("linux", "clock_nanosleep");
+
+        let clk_id = this.read_scalar(clk_id_op)?.to_i32()?;
+        let realtime = clk_id == this.eval_libc_i32("CLOCK_REALTIME")?;
+        // Unlike `nanosleep`, `clock_nanosleep` returns the error number directly and does not
+        // set `errno`.
+        if !realtime && clk_id != this.eval_libc_i32("CLOCK_MONOTONIC")? {
+            return this.eval_libc_i32("EINVAL");
+        }
+
+        let flags = this.read_scalar(flags_op)?.to_i32()?;
+        let abstime = flags == this.eval_libc_i32("TIMER_ABSTIME")?;
+
+        let target = match read_timespec(this, req_op)? {
+            Some(target) => target,
+            None => return this.eval_libc_i32("EINVAL"),
+        };
+        let duration = if abstime {
+            // `target` names an absolute point in time on the *same* clock as `clk_id`; sleep
+            // for whatever is left until then, reading that matching clock (and not e.g. the
+            // monotonic anchor for a `CLOCK_REALTIME` deadline, which would be off by however
+            // long the host has been up).
+            let now = current_clock_reading(this, realtime, "clock_nanosleep")?;
+            target.saturating_sub(now)
+        } else {
+            target
+        };
+
+        advance_virtual_clock(this, duration)?;
+
+        Ok(0)
+    }
+
+    #[allow(non_snake_case)]
+    fn Sleep(&mut self, dwMilliseconds_op: OpTy<'tcx, Tag>) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+
+        this.assert_target_os("windows", "Sleep");
+
+        let dwMilliseconds = this.read_scalar(dwMilliseconds_op)?.to_u32()?;
+        advance_virtual_clock(this, Duration::from_millis(dwMilliseconds.into()))?;
+
+        Ok(())
+    }
+
+    #[allow(non_snake_case)]
+    fn SleepEx(
+        &mut self,
+        dwMilliseconds_op: OpTy<'tcx, Tag>,
+        _bAlertable_op: OpTy<'tcx, Tag>,
+    ) -> InterpResult<'tcx, u32> {
+        let this = self.eval_context_mut();
+
+        this.assert_target_os("windows", "SleepEx");
+
+        let dwMilliseconds = this.read_scalar(dwMilliseconds_op)?.to_u32()?;
+        advance_virtual_clock(this, Duration::from_millis(dwMilliseconds.into()))?;
+
+        // We never model pending I/O completion routines or queued APCs, so an alertable sleep
+        // always runs to completion like a normal one.
+        Ok(0)
+    }
+
     fn mach_absolute_time(&self) -> InterpResult<'tcx, u64> {
         let this = self.eval_context_ref();
 
         this.assert_target_os("macos", "mach_absolute_time");
-        this.check_no_isolation("mach_absolute_time")?;
 
         // This returns a u64, with time units determined dynamically by `mach_timebase_info`.
         // We return plain nanoseconds.
-        let duration = Instant::now().duration_since(this.machine.time_anchor);
+        let duration = if let Some(clock) = &this.machine.deterministic_clock {
+            clock.monotonic()
+        } else {
+            this.check_no_isolation("mach_absolute_time")?;
+            Instant::now().duration_since(this.machine.time_anchor)
+        };
         u64::try_from(duration.as_nanos())
             .map_err(|_| err_unsup_format!("programs running longer than 2^64 nanoseconds are not supported").into())
     }
+
+    fn mach_timebase_info(&mut self, info_op: OpTy<'tcx, Tag>) -> InterpResult<'tcx, i32> {
+        let this = self.eval_context_mut();
+
+        this.assert_target_os("macos", "mach_timebase_info");
+
+        let info = this.deref_operand(info_op)?;
+
+        // Since `mach_absolute_time` already returns plain nanoseconds, `numer / denom` must be
+        // `1` for the standard `duration = ticks * numer / denom` conversion to be a no-op.
+        let imms = [
+            immty_from_uint_checked(1u32, this.layout_of(this.tcx.types.u32)?)?,
+            immty_from_uint_checked(1u32, this.layout_of(this.tcx.types.u32)?)?,
+        ];
+        this.write_packed_immediates(info, &imms)?;
+
+        Ok(0) // KERN_SUCCESS
+    }
 }
@@ -0,0 +1,50 @@
+use rustc_middle::mir;
+
+use crate::stacked_borrows::Tag;
+use crate::*;
+
+impl<'mir, 'tcx> EvalContextExt<'mir, 'tcx> for crate::MiriEvalContext<'mir, 'tcx> {}
+pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx> {
+    /// Dispatches the time-related POSIX foreign items implemented in `shims::time`. Called from
+    /// the unix branch of the top-level `emulate_foreign_item_by_name` match.
+    fn emulate_unix_time_foreign_item(
+        &mut self,
+        link_name: &str,
+        args: &[OpTy<'tcx, Tag>],
+        dest: PlaceTy<'tcx, Tag>,
+        ret: mir::BasicBlock,
+    ) -> InterpResult<'tcx, bool> {
+        let this = self.eval_context_mut();
+
+        match link_name {
+            "clock_gettime" => {
+                let result = this.clock_gettime(args[0], args[1])?;
+                this.write_scalar(Scalar::from_i32(result), dest)?;
+            }
+            "gettimeofday" => {
+                let result = this.gettimeofday(args[0], args[1])?;
+                this.write_scalar(Scalar::from_i32(result), dest)?;
+            }
+            "nanosleep" => {
+                let result = this.nanosleep(args[0], args[1])?;
+                this.write_scalar(Scalar::from_i32(result), dest)?;
+            }
+            "clock_nanosleep" => {
+                let result = this.clock_nanosleep(args[0], args[1], args[2], args[3])?;
+                this.write_scalar(Scalar::from_i32(result), dest)?;
+            }
+            "mach_absolute_time" => {
+                let result = this.mach_absolute_time()?;
+                this.write_scalar(Scalar::from_u64(result), dest)?;
+            }
+            "mach_timebase_info" => {
+                let result = this.mach_timebase_info(args[0])?;
+                this.write_scalar(Scalar::from_i32(result), dest)?;
+            }
+            _ => return Ok(false),
+        }
+
+        this.go_to_block(ret);
+        Ok(true)
+    }
+}
@@ -0,0 +1,44 @@
+use rustc_middle::mir;
+
+use crate::stacked_borrows::Tag;
+use crate::*;
+
+impl<'mir, 'tcx> EvalContextExt<'mir, 'tcx> for crate::MiriEvalContext<'mir, 'tcx> {}
+pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx> {
+    /// Dispatches the time-related Windows foreign items implemented in `shims::time`. Called
+    /// from the windows branch of the top-level `emulate_foreign_item_by_name` match.
+    fn emulate_windows_time_foreign_item(
+        &mut self,
+        link_name: &str,
+        args: &[OpTy<'tcx, Tag>],
+        dest: PlaceTy<'tcx, Tag>,
+        ret: mir::BasicBlock,
+    ) -> InterpResult<'tcx, bool> {
+        let this = self.eval_context_mut();
+
+        match link_name {
+            "GetSystemTimeAsFileTime" => {
+                this.GetSystemTimeAsFileTime(args[0])?;
+            }
+            "Sleep" => {
+                this.Sleep(args[0])?;
+            }
+            "SleepEx" => {
+                let result = this.SleepEx(args[0], args[1])?;
+                this.write_scalar(Scalar::from_u32(result), dest)?;
+            }
+            "QueryPerformanceCounter" => {
+                let result = this.QueryPerformanceCounter(args[0])?;
+                this.write_scalar(Scalar::from_i32(result), dest)?;
+            }
+            "QueryPerformanceFrequency" => {
+                let result = this.QueryPerformanceFrequency(args[0])?;
+                this.write_scalar(Scalar::from_i32(result), dest)?;
+            }
+            _ => return Ok(false),
+        }
+
+        this.go_to_block(ret);
+        Ok(true)
+    }
+}
@@ -0,0 +1,19 @@
+// only-linux: `clock_nanosleep` is only defined for linux
+// compile-flags: -Zmiri-disable-isolation
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn main() {
+    // `CLOCK_REALTIME | TIMER_ABSTIME` deadlines are absolute Unix-epoch timestamps, not small
+    // monotonic-uptime values; computing how long is left to sleep must read the matching
+    // (realtime) clock, or this either sleeps for decades or underflows the internal time anchor.
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    let deadline = now + std::time::Duration::from_secs(1);
+    let ts = libc::timespec { tv_sec: deadline.as_secs() as libc::time_t, tv_nsec: deadline.subsec_nanos() as libc::c_long };
+
+    let ret = unsafe { libc::clock_nanosleep(libc::CLOCK_REALTIME, libc::TIMER_ABSTIME, &ts, std::ptr::null_mut()) };
+    assert_eq!(ret, 0);
+
+    let after = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    assert!(after >= deadline);
+}
@@ -0,0 +1,13 @@
+// only-linux: the `nanosleep`/`clock_nanosleep` shims are only defined for linux
+// compile-flags: -Zmiri-disable-isolation
+
+use std::time::Duration;
+
+fn main() {
+    // A sleeping thread must see `CLOCK_MONOTONIC` (as read through `Instant`) advance by at
+    // least the requested amount, without Miri actually blocking on the host for that long.
+    let before = std::time::Instant::now();
+    std::thread::sleep(Duration::from_secs(3600));
+    let elapsed = before.elapsed();
+    assert!(elapsed >= Duration::from_secs(3600), "monotonic clock did not advance by the sleep duration");
+}
@@ -0,0 +1,12 @@
+// only-windows: `QueryPerformanceCounter`/`QueryPerformanceFrequency` are windows-only
+// compile-flags: -Zmiri-disable-isolation
+
+use std::time::Instant;
+
+fn main() {
+    // `std::time::Instant` on Windows is implemented on top of QPC/QPF, so exercising it through
+    // `std` is enough to cover the shims end-to-end.
+    let before = Instant::now();
+    let after = Instant::now();
+    assert!(after >= before);
+}